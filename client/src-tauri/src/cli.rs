@@ -0,0 +1,199 @@
+//! Headless command-line interface for driving the antivirus client without the tray GUI.
+//!
+//! `main` falls through to this module when the binary is invoked with a subcommand (e.g.
+//! `client scan --path C:\ --deep`), so the same executable works both as the tray app and as
+//! something a scheduled task or CI pipeline can shell out to.
+
+use crate::protocol::{ScanEvent, ScanResult};
+use crate::service_client::ServiceClient;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(name = "client", about = "Antivirus client", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Output format for command results.
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Table,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Ask the service to start a scan.
+    Scan {
+        #[arg(long)]
+        path: String,
+        #[arg(long)]
+        deep: bool,
+    },
+    /// Print the service's current status.
+    Status,
+    /// Inspect persisted settings.
+    Settings {
+        #[command(subcommand)]
+        action: SettingsCommand,
+    },
+    /// Activate a license key for a user.
+    Activate {
+        #[arg(long)]
+        username: String,
+        #[arg(long = "key")]
+        activation_key: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SettingsCommand {
+    Get,
+}
+
+/// Runs a CLI subcommand to completion and returns the process exit code to use.
+///
+/// The GUI drives `ServiceClient` from inside the Tauri-managed Tokio runtime; here there is
+/// no such runtime yet, so we spin up a throwaway one just for this call. `ServiceClient`
+/// uses `block_in_place` for its blocking pipe I/O, which panics on a current-thread runtime,
+/// so this has to be multi-threaded even though the CLI itself never runs tasks concurrently.
+pub fn run(command: Command, format: OutputFormat) -> i32 {
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("failed to start runtime: {e}");
+            return 1;
+        }
+    };
+
+    runtime.block_on(run_async(command, format))
+}
+
+async fn run_async(command: Command, format: OutputFormat) -> i32 {
+    let client = match ServiceClient::new().await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("failed to connect to antivirus service: {e}");
+            return 1;
+        }
+    };
+
+    match command {
+        Command::Scan { path, deep } => match client.start_scan("manual", &path, deep).await {
+            Ok(scan_id) => match wait_for_scan_completion(&scan_id).await {
+                Ok(result) => {
+                    print_result(format, &result);
+                    i32::from(result.total_threats > 0)
+                }
+                Err(e) => {
+                    eprintln!("scan did not complete: {e}");
+                    1
+                }
+            },
+            Err(e) => {
+                eprintln!("scan request failed: {e}");
+                1
+            }
+        },
+        Command::Status => match client.get_status().await {
+            Ok(status) => {
+                let is_running = status.is_running;
+                print_result(format, &status);
+                i32::from(!is_running)
+            }
+            Err(e) => {
+                eprintln!("status request failed: {e}");
+                1
+            }
+        },
+        Command::Settings { action } => match action {
+            SettingsCommand::Get => match client.get_settings().await {
+                Ok(settings) => {
+                    print_result(format, &settings);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("settings request failed: {e}");
+                    1
+                }
+            },
+        },
+        Command::Activate {
+            username,
+            activation_key,
+        } => match client.activate_license(&username, &activation_key).await {
+            Ok(result) => {
+                let activated = result.activated;
+                print_result(format, &result);
+                i32::from(!activated)
+            }
+            Err(e) => {
+                eprintln!("activation failed: {e}");
+                1
+            }
+        },
+    }
+}
+
+/// Subscribes to `scan_id`'s progress stream and blocks until its `ScanComplete` event
+/// arrives, returning the final result. `stream_scan_events` drives the callback on a
+/// blocking task, so the result is bridged back to this `await` through a oneshot channel.
+async fn wait_for_scan_completion(
+    scan_id: &str,
+) -> Result<ScanResult, crate::service_client::ServiceClientError> {
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+    let mut result_tx = Some(result_tx);
+
+    ServiceClient::stream_scan_events(scan_id, move |event| {
+        if let ScanEvent::ScanComplete(result) = event {
+            if let Some(result_tx) = result_tx.take() {
+                let _ = result_tx.send(result);
+            }
+        }
+    })
+    .await?;
+
+    result_rx.await.map_err(|_| {
+        crate::service_client::ServiceClientError::CommunicationError(
+            "scan event stream ended without a completion event".to_string(),
+        )
+    })
+}
+
+fn print_result<T: Serialize>(format: OutputFormat, value: &T) {
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(value) {
+            Ok(json) => println!("{json}"),
+            Err(e) => eprintln!("failed to format result: {e}"),
+        },
+        OutputFormat::Table => print_table(value),
+    }
+}
+
+fn print_table<T: Serialize>(value: &T) {
+    let value = match serde_json::to_value(value) {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("failed to format result: {e}");
+            return;
+        }
+    };
+
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, val) in fields {
+                println!("{key}: {val}");
+            }
+        }
+        other => println!("{other}"),
+    }
+}
@@ -0,0 +1,102 @@
+//! Length-prefixed framing for the named-pipe wire format.
+//!
+//! Every frame is `length: u32 (big-endian) || payload`, where `length` counts only the
+//! bytes that follow. A single `read` can return fewer bytes than a full message (the pipe
+//! has no concept of message boundaries on its own), so readers must loop until they have
+//! exactly `length` bytes rather than trusting one `read` call to return everything.
+
+use std::io::{self, Read, Write};
+
+/// Default cap on a claimed frame length, so a corrupt or hostile peer can't make us
+/// allocate an arbitrary amount of memory for the payload buffer. Callers that need a different ceiling
+/// (e.g. a deployment that expects larger scan results) can call `read_frame_with_max` instead
+/// of `read_frame` to override it.
+pub const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame payload too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(payload)
+}
+
+/// Reads a frame, rejecting a claimed length over `MAX_FRAME_LEN`. See `read_frame_with_max`
+/// to use a different limit.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    read_frame_with_max(reader, MAX_FRAME_LEN)
+}
+
+/// Reads a frame, rejecting a claimed length over `max_len`.
+pub fn read_frame_with_max<R: Read>(reader: &mut R, max_len: u32) -> io::Result<Vec<u8>> {
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes)?;
+    let len = u32::from_be_bytes(len_bytes);
+
+    if len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame length {len} exceeds max of {max_len}"),
+        ));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trips_a_payload() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"hello world").unwrap();
+
+        let payload = read_frame(&mut Cursor::new(buf)).unwrap();
+
+        assert_eq!(payload, b"hello world");
+    }
+
+    #[test]
+    fn round_trips_an_empty_payload() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"").unwrap();
+
+        let payload = read_frame(&mut Cursor::new(buf)).unwrap();
+
+        assert_eq!(payload, b"");
+    }
+
+    #[test]
+    fn rejects_a_length_over_the_max() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(MAX_FRAME_LEN + 1).to_be_bytes());
+
+        let err = read_frame(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_frame_with_max_honors_a_custom_limit() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"hello").unwrap();
+
+        let err = read_frame_with_max(&mut Cursor::new(buf), 4).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_a_truncated_payload() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&10u32.to_be_bytes());
+        buf.extend_from_slice(b"short");
+
+        let err = read_frame(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,21 +1,50 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::sync::{Arc, Mutex};
+use clap::Parser;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tauri::{
     AppHandle, CustomMenuItem, Manager, State, SystemTray, SystemTrayEvent, SystemTrayMenu,
     SystemTrayMenuItem, Window,
 };
+use tokio::sync::{Mutex, RwLock};
 
+mod cli;
+mod crypto;
+mod framing;
 mod service_client;
 mod protocol;
 
 use service_client::ServiceClient;
 
-// Application state
+// Application state.
+//
+// `service_client` is an `RwLock` (not `std::sync::Mutex`) because every command below
+// holds the guard across an `.await` on the pipe round-trip; a std mutex guard held across
+// an await point can deadlock the Tokio runtime and serializes unrelated IPC calls behind
+// whichever one is in flight. Readers (the common case - every command just needs a live
+// `ServiceClient` to call into) can run concurrently; only (re)connecting takes the write lock.
+// The pipe round-trips themselves are still serialized: `ServiceClient` holds its own request
+// lock for each write-then-read exchange on its single pipe handle.
+//
+// `real_time_protection` mirrors the last known value from `get_status` in a plain atomic. It
+// is written by `get_status` only; no command returns it. `connected` is set on the initial connect attempt in `setup()` and then kept live by
+// `get_status`, which flips it back to false if a round-trip fails - it reflects the pipe's
+// state as of the last observed I/O, not a continuously-updated heartbeat, so it can still lag
+// behind an actual disconnect until something next calls `get_status`.
+//
+// `scan_watchers` holds the abort handle for each scan's progress-streaming task, keyed by
+// scan_id, so `cancel_scan` can stop listening immediately instead of waiting on a service
+// that may never send a final event for a cancelled scan. Entries are removed both by
+// `cancel_scan` and by the watcher task itself once it completes normally.
 #[derive(Default)]
 struct AppState {
-    service_client: Arc<Mutex<Option<ServiceClient>>>,
+    service_client: Arc<RwLock<Option<ServiceClient>>>,
+    connected: AtomicBool,
+    real_time_protection: AtomicBool,
+    scan_watchers: Mutex<HashMap<String, tauri::async_runtime::JoinHandle<()>>>,
 }
 
 // Tauri commands
@@ -25,7 +54,7 @@ async fn authenticate(
     password: String,
     state: State<'_, AppState>,
 ) -> Result<bool, String> {
-    let client = state.service_client.lock().unwrap();
+    let client = state.service_client.read().await;
     if let Some(client) = client.as_ref() {
         client.authenticate(&username, &password).await.map_err(|e| e.to_string())
     } else {
@@ -38,7 +67,7 @@ async fn check_license(
     username: String,
     state: State<'_, AppState>,
 ) -> Result<protocol::LicenseInfo, String> {
-    let client = state.service_client.lock().unwrap();
+    let client = state.service_client.read().await;
     if let Some(client) = client.as_ref() {
         client.check_license(&username).await.map_err(|e| e.to_string())
     } else {
@@ -52,7 +81,7 @@ async fn activate_license(
     activation_key: String,
     state: State<'_, AppState>,
 ) -> Result<protocol::ActivationResult, String> {
-    let client = state.service_client.lock().unwrap();
+    let client = state.service_client.read().await;
     if let Some(client) = client.as_ref() {
         client.activate_license(&username, &activation_key).await.map_err(|e| e.to_string())
     } else {
@@ -60,38 +89,147 @@ async fn activate_license(
     }
 }
 
+// Payloads for the `scan://progress` and `scan://complete` events. `ServiceClient` can have
+// more than one scan in flight (or a stale stream still draining after cancellation), so
+// every event carries the scan_id it belongs to rather than leaving listeners to guess.
+#[derive(Clone, serde::Serialize)]
+struct ScanProgressEvent {
+    scan_id: String,
+    progress: protocol::ScanProgress,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct ScanCompleteEvent {
+    scan_id: String,
+    result: protocol::ScanResult,
+}
+
 #[tauri::command]
 async fn start_scan(
     scan_type: String,
     path: String,
     deep_scan: bool,
+    app_handle: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let client = state.service_client.lock().unwrap();
-    if let Some(client) = client.as_ref() {
-        client.start_scan(&scan_type, &path, deep_scan).await.map_err(|e| e.to_string())
-    } else {
-        Err("Service client not connected".to_string())
+    let scan_id = {
+        let client = state.service_client.read().await;
+        if let Some(client) = client.as_ref() {
+            client.start_scan(&scan_type, &path, deep_scan).await.map_err(|e| e.to_string())?
+        } else {
+            return Err("Service client not connected".to_string());
+        }
+    };
+
+    // Stream this scan's progress to the frontend over its own connection rather than
+    // blocking `start_scan` itself until the scan finishes. Events are tagged with scan_id
+    // since more than one scan can be in flight, and listeners need to tell them apart.
+    //
+    // Hold `scan_watchers` across both the spawn and the insert below: the watcher task's own
+    // cleanup also needs this lock to remove its entry, so even if the task runs to completion
+    // before we get back here, its cleanup blocks on the lock until we've inserted - closing
+    // the window where cleanup could run ahead of registration and leak a stale entry.
+    let mut watchers = state.scan_watchers.lock().await;
+
+    let watched_scan_id = scan_id.clone();
+    let watcher_handle = tauri::async_runtime::spawn(async move {
+        let cleanup_handle = app_handle.clone();
+        let result = ServiceClient::stream_scan_events(&watched_scan_id, {
+            let scan_id = watched_scan_id.clone();
+            move |event| match event {
+                protocol::ScanEvent::ScanProgress(progress) => {
+                    let _ = app_handle.emit_all(
+                        "scan://progress",
+                        ScanProgressEvent {
+                            scan_id: scan_id.clone(),
+                            progress,
+                        },
+                    );
+                }
+                protocol::ScanEvent::ScanComplete(result) => {
+                    let _ = app_handle.emit_all(
+                        "scan://complete",
+                        ScanCompleteEvent {
+                            scan_id: scan_id.clone(),
+                            result,
+                        },
+                    );
+                }
+            }
+        })
+        .await;
+
+        if let Err(e) = result {
+            log::error!("scan progress stream for {watched_scan_id} ended with error: {e}");
+        }
+
+        let state: State<AppState> = cleanup_handle.state();
+        state.scan_watchers.lock().await.remove(&watched_scan_id);
+    });
+
+    watchers.insert(scan_id.clone(), watcher_handle);
+    drop(watchers);
+
+    Ok(scan_id)
+}
+
+#[tauri::command]
+async fn cancel_scan(scan_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let result = {
+        let client = state.service_client.read().await;
+        if let Some(client) = client.as_ref() {
+            client.cancel_scan(&scan_id).await.map_err(|e| e.to_string())
+        } else {
+            Err("Service client not connected".to_string())
+        }
+    };
+
+    // The service may never emit a final event for a cancelled scan, so don't rely on the
+    // watcher task noticing on its own - abort it directly now that cancellation was requested.
+    if let Some(handle) = state.scan_watchers.lock().await.remove(&scan_id) {
+        handle.abort();
     }
+
+    result
 }
 
 #[tauri::command]
 async fn get_status(
     state: State<'_, AppState>,
 ) -> Result<protocol::ServiceStatus, String> {
-    let client = state.service_client.lock().unwrap();
+    let client = state.service_client.read().await;
     if let Some(client) = client.as_ref() {
-        client.get_status().await.map_err(|e| e.to_string())
+        let status = match client.get_status().await {
+            Ok(status) => status,
+            Err(e) => {
+                // A failed round-trip is the one place we actually observe the pipe having
+                // gone away, so this is where `connected` needs to flip back to false.
+                state.connected.store(false, Ordering::Relaxed);
+                return Err(e.to_string());
+            }
+        };
+        state.connected.store(true, Ordering::Relaxed);
+        state
+            .real_time_protection
+            .store(status.real_time_protection, Ordering::Relaxed);
+        Ok(status)
     } else {
         Err("Service client not connected".to_string())
     }
 }
 
+// Cheap, lock-free poll of the last known connection state, for UI badges that
+// refresh on a timer without forcing a pipe round-trip on every tick.
+#[tauri::command]
+fn is_connected(state: State<'_, AppState>) -> bool {
+    state.connected.load(Ordering::Relaxed)
+}
+
 #[tauri::command]
 async fn get_settings(
     state: State<'_, AppState>,
 ) -> Result<protocol::Settings, String> {
-    let client = state.service_client.lock().unwrap();
+    let client = state.service_client.read().await;
     if let Some(client) = client.as_ref() {
         client.get_settings().await.map_err(|e| e.to_string())
     } else {
@@ -104,7 +242,7 @@ async fn update_settings(
     settings: protocol::Settings,
     state: State<'_, AppState>,
 ) -> Result<bool, String> {
-    let client = state.service_client.lock().unwrap();
+    let client = state.service_client.read().await;
     if let Some(client) = client.as_ref() {
         client.update_settings(settings).await.map_err(|e| e.to_string())
     } else {
@@ -140,6 +278,11 @@ fn create_system_tray() -> SystemTray {
 }
 
 fn main() {
+    let cli = cli::Cli::parse();
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command, cli.format));
+    }
+
     env_logger::init();
 
     let tray = create_system_tray();
@@ -204,14 +347,15 @@ fn main() {
             // Initialize service client connection
             tauri::async_runtime::spawn(async move {
                 let state: State<AppState> = app_handle.state();
-                let mut client_guard = state.service_client.lock().unwrap();
-                
+
                 match ServiceClient::new().await {
                     Ok(client) => {
-                        *client_guard = Some(client);
+                        *state.service_client.write().await = Some(client);
+                        state.connected.store(true, Ordering::Relaxed);
                         log::info!("Successfully connected to antivirus service");
                     }
                     Err(e) => {
+                        state.connected.store(false, Ordering::Relaxed);
                         log::error!("Failed to connect to antivirus service: {}", e);
                         // Show error notification
                         if let Err(e) = app_handle.notification()
@@ -232,9 +376,11 @@ fn main() {
             check_license,
             activate_license,
             start_scan,
+            cancel_scan,
             get_status,
             get_settings,
             update_settings,
+            is_connected,
             show_main_window,
             hide_main_window
         ])
@@ -1,7 +1,24 @@
+use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
 
 pub const PIPE_NAME: &str = r"\\.\pipe\AntivirusService";
 
+/// Coarse category carried as the first byte of each message, ahead of the JSON body, inside
+/// the encrypted payload (the frame's length prefix carries only the payload length).
+///
+/// This lets a response be checked against what was requested (and a framing/version
+/// mismatch rejected outright) before we even attempt to parse the body as JSON, instead of
+/// relying solely on the stringly-typed `"type"` field inside the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum MessageKind {
+    Auth = 0,
+    License = 1,
+    Scan = 2,
+    Status = 3,
+    Settings = 4,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LicenseInfo {
     pub is_valid: bool,
@@ -62,3 +79,12 @@ pub struct ScanResult {
     pub total_threats: u32,
     pub threats: Vec<ThreatInfo>,
 }
+
+/// A message pushed by the service on the scan-progress subscription opened after
+/// `start_scan`, until the scan finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScanEvent {
+    ScanProgress(ScanProgress),
+    ScanComplete(ScanResult),
+}
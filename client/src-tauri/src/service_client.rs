@@ -1,230 +1,539 @@
-use crate::protocol::*;
-use std::io::{Read, Write};
-use std::os::windows::io::{AsRawHandle, FromRawHandle};
-use thiserror::Error;
-use windows::{
-    core::PCSTR,
-    Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE},
-    Win32::System::Pipes::{CreateFileA, PIPE_ACCESS_DUPLEX},
-    Win32::Storage::FileSystem::{
-        CREATE_ALWAYS, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
-    },
-};
-
-#[derive(Error, Debug)]
-pub enum ServiceClientError {
-    #[error("Failed to connect to service: {0}")]
-    ConnectionFailed(String),
-    #[error("Communication error: {0}")]
-    CommunicationError(String),
-    #[error("Serialization error: {0}")]
-    SerializationError(String),
-    #[error("Service returned error: {0}")]
-    ServiceError(String),
-}
-
-pub struct ServiceClient {
-    pipe_handle: HANDLE,
-}
-
-impl ServiceClient {
-    pub async fn new() -> Result<Self, ServiceClientError> {
-        let pipe_name = PIPE_NAME;
-        
-        // Convert to PCSTR
-        let pipe_name_cstr = std::ffi::CString::new(pipe_name)
-            .map_err(|e| ServiceClientError::ConnectionFailed(e.to_string()))?;
-        
-        unsafe {
-            let handle = CreateFileA(
-                PCSTR(pipe_name_cstr.as_ptr() as *const u8),
-                FILE_SHARE_READ.0 | FILE_SHARE_WRITE.0,
-                None,
-                OPEN_EXISTING,
-                FILE_ATTRIBUTE_NORMAL,
-                HANDLE::default(),
-            );
-
-            if handle == INVALID_HANDLE_VALUE {
-                return Err(ServiceClientError::ConnectionFailed(
-                    "Failed to connect to named pipe".to_string(),
-                ));
-            }
-
-            Ok(ServiceClient {
-                pipe_handle: handle,
-            })
-        }
-    }
-
-    pub async fn authenticate(&self, username: &str, password: &str) -> Result<bool, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "auth_request",
-            "username": username,
-            "password": password
-        });
-
-        let response = self.send_request(request).await?;
-        
-        if let Some(result) = response.get("result").and_then(|v| v.as_bool()) {
-            Ok(result)
-        } else {
-            Err(ServiceClientError::ServiceError(
-                response.get("message")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Authentication failed")
-                    .to_string()
-            ))
-        }
-    }
-
-    pub async fn check_license(&self, username: &str) -> Result<LicenseInfo, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "license_check",
-            "username": username
-        });
-
-        let response = self.send_request(request).await?;
-        
-        Ok(LicenseInfo {
-            is_valid: response.get("is_valid").and_then(|v| v.as_bool()).unwrap_or(false),
-            expires_at: response.get("expires_at").and_then(|v| v.as_u64()).unwrap_or(0),
-            license_type: response.get("license_type").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-            message: response.get("message").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-        })
-    }
-
-    pub async fn activate_license(&self, username: &str, activation_key: &str) -> Result<ActivationResult, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "activate_request",
-            "username": username,
-            "activation_key": activation_key
-        });
-
-        let response = self.send_request(request).await?;
-        
-        Ok(ActivationResult {
-            activated: response.get("activated").and_then(|v| v.as_bool()).unwrap_or(false),
-            expires_at: response.get("expires_at").and_then(|v| v.as_u64()).unwrap_or(0),
-            message: response.get("message").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-        })
-    }
-
-    pub async fn start_scan(&self, scan_type: &str, path: &str, deep_scan: bool) -> Result<String, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "scan_request",
-            "scan_type": scan_type,
-            "path": path,
-            "deep_scan": deep_scan
-        });
-
-        let response = self.send_request(request).await?;
-        
-        Ok(response.get("scan_id")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string())
-    }
-
-    pub async fn get_status(&self) -> Result<ServiceStatus, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "status_request"
-        });
-
-        let response = self.send_request(request).await?;
-        
-        Ok(ServiceStatus {
-            is_running: response.get("is_running").and_then(|v| v.as_bool()).unwrap_or(false),
-            real_time_protection: response.get("real_time_protection").and_then(|v| v.as_bool()).unwrap_or(false),
-            auto_scan_enabled: response.get("auto_scan_enabled").and_then(|v| v.as_bool()).unwrap_or(false),
-            last_scan_time: response.get("last_scan_time").and_then(|v| v.as_u64()).unwrap_or(0),
-            last_update_time: response.get("last_update_time").and_then(|v| v.as_u64()).unwrap_or(0),
-            database_version: response.get("database_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
-            total_threats_blocked: response.get("total_threats_blocked").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
-        })
-    }
-
-    pub async fn get_settings(&self) -> Result<Settings, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "settings_get"
-        });
-
-        let response = self.send_request(request).await?;
-        
-        let settings = response.get("settings").ok_or_else(|| {
-            ServiceClientError::ServiceError("No settings in response".to_string())
-        })?;
-
-        Ok(Settings {
-            real_time_protection: settings.get("real_time_protection").and_then(|v| v.as_bool()).unwrap_or(true),
-            scan_on_access: settings.get("scan_on_access").and_then(|v| v.as_bool()).unwrap_or(true),
-            scan_archives: settings.get("scan_archives").and_then(|v| v.as_bool()).unwrap_or(false),
-            auto_update: settings.get("auto_update").and_then(|v| v.as_bool()).unwrap_or(true),
-            scan_schedule: settings.get("scan_schedule").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
-            scan_time: settings.get("scan_time").and_then(|v| v.as_u64()).unwrap_or(2) as u32,
-            quarantine_path: settings.get("quarantine_path").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-            exclusion_paths: settings.get("exclusion_paths").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-        })
-    }
-
-    pub async fn update_settings(&self, settings: Settings) -> Result<bool, ServiceClientError> {
-        let request = serde_json::json!({
-            "type": "settings_set",
-            "settings": {
-                "real_time_protection": settings.real_time_protection,
-                "scan_on_access": settings.scan_on_access,
-                "scan_archives": settings.scan_archives,
-                "auto_update": settings.auto_update,
-                "scan_schedule": settings.scan_schedule,
-                "scan_time": settings.scan_time,
-                "quarantine_path": settings.quarantine_path,
-                "exclusion_paths": settings.exclusion_paths
-            }
-        });
-
-        let response = self.send_request(request).await?;
-        
-        Ok(response.get("success").and_then(|v| v.as_bool()).unwrap_or(false))
-    }
-
-    async fn send_request(&self, request: serde_json::Value) -> Result<serde_json::Value, ServiceClientError> {
-        let request_str = serde_json::to_string(&request)
-            .map_err(|e| ServiceClientError::SerializationError(e.to_string()))?;
-
-        // Convert HANDLE to std::fs::File for easier I/O
-        let mut file = unsafe { 
-            std::fs::File::from_raw_handle(self.pipe_handle.0 as *mut std::ffi::c_void) 
-        };
-
-        // Send request
-        file.write_all(request_str.as_bytes())
-            .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
-        
-        // Read response
-        let mut response_buffer = vec![0u8; 4096];
-        let bytes_read = file.read(&mut response_buffer)
-            .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
-        
-        response_buffer.truncate(bytes_read);
-        let response_str = String::from_utf8(response_buffer)
-            .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
-
-        let response: serde_json::Value = serde_json::from_str(&response_str)
-            .map_err(|e| ServiceClientError::SerializationError(e.to_string()))?;
-
-        // Forget the file so we don't close the handle
-        std::mem::forget(file);
-
-        Ok(response)
-    }
-}
-
-impl Drop for ServiceClient {
-    fn drop(&mut self) {
-        if self.pipe_handle != INVALID_HANDLE_VALUE {
-            unsafe {
-                CloseHandle(self.pipe_handle);
-            }
-        }
-    }
-}
+use crate::crypto::{HandshakeState, SecureChannel};
+use crate::framing::{read_frame, write_frame};
+use crate::protocol::*;
+use ed25519_dalek::SIGNATURE_LENGTH;
+use std::io::{Read, Write};
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+use thiserror::Error;
+use zeroize::Zeroize;
+use windows::{
+    core::PCSTR,
+    Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE},
+    Win32::System::Pipes::{CreateFileA, GetNamedPipeServerProcessId, PIPE_ACCESS_DUPLEX},
+    Win32::Storage::FileSystem::{
+        CREATE_ALWAYS, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    },
+};
+
+// Installed location of the antivirus service binary. The pipe is a well-known name, so
+// anything local can race the real service to create it; the only way to tell a genuine
+// server apart from an impersonator is to check which process actually owns the handle we
+// connected to.
+const EXPECTED_SERVICE_EXE: &str = r"C:\Program Files\AntivirusService\AntivirusService.exe";
+
+#[derive(Error, Debug)]
+pub enum ServiceClientError {
+    #[error("Failed to connect to service: {0}")]
+    ConnectionFailed(String),
+    #[error("Communication error: {0}")]
+    CommunicationError(String),
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+    #[error("Service returned error: {0}")]
+    ServiceError(String),
+    #[error("Refusing to talk to untrusted pipe server: {0}")]
+    UntrustedPeer(String),
+    #[error("Could not verify identity of pipe server: {0}")]
+    PeerVerificationUnavailable(String),
+    #[error("Secure handshake with service failed: {0}")]
+    HandshakeFailed(String),
+}
+
+pub struct ServiceClient {
+    pipe_handle: HANDLE,
+    channel: SecureChannel,
+    // Guards the seal -> write -> read -> open exchange on `pipe_handle`. The outer
+    // `AppState` only wraps *this* struct in an `RwLock`, which lets multiple commands hold
+    // it concurrently by design (that's the point of request #1); without a lock in here too,
+    // two requests in flight at once could interleave their frames on the same handle.
+    request_lock: tokio::sync::Mutex<()>,
+}
+
+impl ServiceClient {
+    pub async fn new() -> Result<Self, ServiceClientError> {
+        // `CreateFileA`, the peer-identity check, and the handshake are all synchronous
+        // syscalls; run them via `block_in_place` rather than directly in this async fn so
+        // they don't block whichever Tokio worker thread happens to be polling us.
+        tokio::task::block_in_place(Self::connect_blocking)
+    }
+
+    fn connect_blocking() -> Result<Self, ServiceClientError> {
+        let pipe_name = PIPE_NAME;
+
+        // Convert to PCSTR
+        let pipe_name_cstr = std::ffi::CString::new(pipe_name)
+            .map_err(|e| ServiceClientError::ConnectionFailed(e.to_string()))?;
+
+        unsafe {
+            let handle = CreateFileA(
+                PCSTR(pipe_name_cstr.as_ptr() as *const u8),
+                FILE_SHARE_READ.0 | FILE_SHARE_WRITE.0,
+                None,
+                OPEN_EXISTING,
+                FILE_ATTRIBUTE_NORMAL,
+                HANDLE::default(),
+            );
+
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(ServiceClientError::ConnectionFailed(
+                    "Failed to connect to named pipe".to_string(),
+                ));
+            }
+
+            if let Err(e) = Self::verify_peer(handle) {
+                CloseHandle(handle);
+                return Err(e);
+            }
+
+            let channel = match Self::handshake(handle) {
+                Ok(channel) => channel,
+                Err(e) => {
+                    CloseHandle(handle);
+                    return Err(e);
+                }
+            };
+
+            Ok(ServiceClient {
+                pipe_handle: handle,
+                channel,
+                request_lock: tokio::sync::Mutex::new(()),
+            })
+        }
+    }
+
+    /// Performs the X25519/ed25519 handshake described in `crypto` and returns the resulting
+    /// `SecureChannel`. The handshake does not use the length-prefixed framing: both handshake
+    /// messages are fixed-size, written with `write_all` and read with `read_exact`.
+    unsafe fn handshake(handle: HANDLE) -> Result<SecureChannel, ServiceClientError> {
+        let mut file = std::fs::File::from_raw_handle(handle.0 as *mut std::ffi::c_void);
+
+        let state = HandshakeState::new();
+        let write_result = file.write_all(&state.public_bytes);
+        if let Err(e) = write_result {
+            std::mem::forget(file);
+            return Err(ServiceClientError::HandshakeFailed(e.to_string()));
+        }
+
+        // server ephemeral public key (32 bytes) || ed25519 signature over it (64 bytes)
+        let mut server_hello = [0u8; 32 + SIGNATURE_LENGTH];
+        let read_result = file.read_exact(&mut server_hello);
+        std::mem::forget(file);
+        read_result.map_err(|e| ServiceClientError::HandshakeFailed(e.to_string()))?;
+
+        let mut server_public_bytes = [0u8; 32];
+        server_public_bytes.copy_from_slice(&server_hello[..32]);
+        let mut signature_bytes = [0u8; SIGNATURE_LENGTH];
+        signature_bytes.copy_from_slice(&server_hello[32..]);
+
+        state
+            .finish(server_public_bytes, signature_bytes)
+            .map_err(|e| ServiceClientError::HandshakeFailed(e.to_string()))
+    }
+
+    /// Confirms the process on the other end of `handle` is the installed antivirus
+    /// service, not merely something that won the race to create the pipe name first.
+    ///
+    /// This assumes querying another process's image path via `sysinfo` (itself backed by
+    /// `QueryFullProcessImageName`) succeeds for a SYSTEM-level service from an unprivileged
+    /// client; that assumption needs to be validated against the real installed service, since
+    /// a silent access-denied failure here would be indistinguishable from the PID simply not
+    /// resolving. Until then, a failure to even query the peer is treated as inconclusive
+    /// (`PeerVerificationUnavailable`) rather than as proof of an untrusted peer - reserve
+    /// `UntrustedPeer` for a confirmed mismatch against `EXPECTED_SERVICE_EXE`.
+    unsafe fn verify_peer(handle: HANDLE) -> Result<(), ServiceClientError> {
+        let mut server_pid: u32 = 0;
+        GetNamedPipeServerProcessId(handle, &mut server_pid).map_err(|e| {
+            ServiceClientError::PeerVerificationUnavailable(format!(
+                "could not resolve pipe server pid: {e}"
+            ))
+        })?;
+
+        let image_path = Self::process_image_path(server_pid).ok_or_else(|| {
+            ServiceClientError::PeerVerificationUnavailable(format!(
+                "could not resolve image path for pid {server_pid}"
+            ))
+        })?;
+
+        let expected = Path::new(EXPECTED_SERVICE_EXE);
+        if !paths_equal(&image_path, expected) {
+            return Err(ServiceClientError::UntrustedPeer(format!(
+                "pipe is owned by '{}', expected '{}'",
+                image_path.display(),
+                expected.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a PID to its executable path. Uses `sysinfo` rather than hand-rolled
+    /// `QueryFullProcessImageName` calls. Returns `None` if the process isn't found or its
+    /// image path couldn't be read (which `sysinfo` reports as an empty path).
+    fn process_image_path(pid: u32) -> Option<PathBuf> {
+        let mut system = System::new();
+        let pid = Pid::from_u32(pid);
+        system.refresh_process(pid);
+        system
+            .process(pid)
+            .map(|p| p.exe().to_path_buf())
+            .filter(|path| !path.as_os_str().is_empty())
+    }
+
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<bool, ServiceClientError> {
+        #[derive(serde::Serialize)]
+        struct AuthRequest<'a> {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            username: &'a str,
+            password: &'a str,
+        }
+
+        // Serialize straight to a JSON string and seal it ourselves instead of going through
+        // `send_request`'s `serde_json::Value`, whose backing `String` wouldn't get zeroized -
+        // `seal_json` takes ownership of this string and zeroizes it once sealed.
+        let json = serde_json::to_string(&AuthRequest {
+            kind: "auth_request",
+            username,
+            password,
+        })
+        .map_err(|e| ServiceClientError::SerializationError(e.to_string()))?;
+        let sealed_request = self.seal_json(MessageKind::Auth, json)?;
+
+        let response = self.send_sealed(MessageKind::Auth, sealed_request).await?;
+        
+        if let Some(result) = response.get("result").and_then(|v| v.as_bool()) {
+            Ok(result)
+        } else {
+            Err(ServiceClientError::ServiceError(
+                response.get("message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Authentication failed")
+                    .to_string()
+            ))
+        }
+    }
+
+    pub async fn check_license(&self, username: &str) -> Result<LicenseInfo, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "license_check",
+            "username": username
+        });
+
+        let response = self.send_request(MessageKind::License, request).await?;
+        
+        Ok(LicenseInfo {
+            is_valid: response.get("is_valid").and_then(|v| v.as_bool()).unwrap_or(false),
+            expires_at: response.get("expires_at").and_then(|v| v.as_u64()).unwrap_or(0),
+            license_type: response.get("license_type").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+            message: response.get("message").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        })
+    }
+
+    pub async fn activate_license(&self, username: &str, activation_key: &str) -> Result<ActivationResult, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "activate_request",
+            "username": username,
+            "activation_key": activation_key
+        });
+
+        let response = self.send_request(MessageKind::License, request).await?;
+        
+        Ok(ActivationResult {
+            activated: response.get("activated").and_then(|v| v.as_bool()).unwrap_or(false),
+            expires_at: response.get("expires_at").and_then(|v| v.as_u64()).unwrap_or(0),
+            message: response.get("message").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        })
+    }
+
+    pub async fn start_scan(&self, scan_type: &str, path: &str, deep_scan: bool) -> Result<String, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "scan_request",
+            "scan_type": scan_type,
+            "path": path,
+            "deep_scan": deep_scan
+        });
+
+        let response = self.send_request(MessageKind::Scan, request).await?;
+        
+        Ok(response.get("scan_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string())
+    }
+
+    pub async fn cancel_scan(&self, scan_id: &str) -> Result<bool, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "scan_cancel",
+            "scan_id": scan_id
+        });
+
+        let response = self.send_request(MessageKind::Scan, request).await?;
+
+        Ok(response.get("success").and_then(|v| v.as_bool()).unwrap_or(false))
+    }
+
+    /// Opens a dedicated connection to the service and invokes `on_event` for every scan
+    /// event pushed for `scan_id`, returning once a `ScanEvent::ScanComplete` arrives.
+    ///
+    /// Runs on its own pipe connection (its own handshake, its own session key) rather than
+    /// `self`'s, since `send_request` assumes a strict one-write-one-read exchange and
+    /// progress events arrive as an open-ended stream instead. The read loop blocks on
+    /// synchronous pipe I/O for as long as the scan runs, so it's dispatched onto a blocking
+    /// thread rather than tying up one of the async runtime's worker threads.
+    pub async fn stream_scan_events(
+        scan_id: &str,
+        on_event: impl FnMut(ScanEvent) + Send + 'static,
+    ) -> Result<(), ServiceClientError> {
+        let watcher = ServiceClient::new().await?;
+        let scan_id = scan_id.to_string();
+
+        tokio::task::spawn_blocking(move || watcher.run_scan_event_loop(&scan_id, on_event))
+            .await
+            .map_err(|e| {
+                ServiceClientError::CommunicationError(format!(
+                    "scan event loop task panicked: {e}"
+                ))
+            })?
+    }
+
+    fn run_scan_event_loop(
+        &self,
+        scan_id: &str,
+        mut on_event: impl FnMut(ScanEvent),
+    ) -> Result<(), ServiceClientError> {
+        let subscribe = serde_json::json!({
+            "type": "scan_subscribe",
+            "scan_id": scan_id
+        });
+        let sealed = self.seal_message(MessageKind::Scan, &subscribe)?;
+
+        let mut file = unsafe {
+            std::fs::File::from_raw_handle(self.pipe_handle.0 as *mut std::ffi::c_void)
+        };
+
+        let io_result = (|| -> Result<(), ServiceClientError> {
+            write_frame(&mut file, &sealed)
+                .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
+
+            loop {
+                let frame = read_frame(&mut file)
+                    .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
+                let (kind, body) = self.open_message(&frame)?;
+                if kind != MessageKind::Scan {
+                    return Err(ServiceClientError::CommunicationError(format!(
+                        "unexpected message kind {kind:?} in scan event stream"
+                    )));
+                }
+
+                let event: ScanEvent = serde_json::from_slice(&body)
+                    .map_err(|e| ServiceClientError::SerializationError(e.to_string()))?;
+
+                let is_complete = matches!(event, ScanEvent::ScanComplete(_));
+                on_event(event);
+                if is_complete {
+                    break;
+                }
+            }
+
+            Ok(())
+        })();
+
+        std::mem::forget(file);
+        io_result
+    }
+
+    pub async fn get_status(&self) -> Result<ServiceStatus, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "status_request"
+        });
+
+        let response = self.send_request(MessageKind::Status, request).await?;
+        
+        Ok(ServiceStatus {
+            is_running: response.get("is_running").and_then(|v| v.as_bool()).unwrap_or(false),
+            real_time_protection: response.get("real_time_protection").and_then(|v| v.as_bool()).unwrap_or(false),
+            auto_scan_enabled: response.get("auto_scan_enabled").and_then(|v| v.as_bool()).unwrap_or(false),
+            last_scan_time: response.get("last_scan_time").and_then(|v| v.as_u64()).unwrap_or(0),
+            last_update_time: response.get("last_update_time").and_then(|v| v.as_u64()).unwrap_or(0),
+            database_version: response.get("database_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+            total_threats_blocked: response.get("total_threats_blocked").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+        })
+    }
+
+    pub async fn get_settings(&self) -> Result<Settings, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "settings_get"
+        });
+
+        let response = self.send_request(MessageKind::Settings, request).await?;
+        
+        let settings = response.get("settings").ok_or_else(|| {
+            ServiceClientError::ServiceError("No settings in response".to_string())
+        })?;
+
+        Ok(Settings {
+            real_time_protection: settings.get("real_time_protection").and_then(|v| v.as_bool()).unwrap_or(true),
+            scan_on_access: settings.get("scan_on_access").and_then(|v| v.as_bool()).unwrap_or(true),
+            scan_archives: settings.get("scan_archives").and_then(|v| v.as_bool()).unwrap_or(false),
+            auto_update: settings.get("auto_update").and_then(|v| v.as_bool()).unwrap_or(true),
+            scan_schedule: settings.get("scan_schedule").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+            scan_time: settings.get("scan_time").and_then(|v| v.as_u64()).unwrap_or(2) as u32,
+            quarantine_path: settings.get("quarantine_path").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+            exclusion_paths: settings.get("exclusion_paths").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        })
+    }
+
+    pub async fn update_settings(&self, settings: Settings) -> Result<bool, ServiceClientError> {
+        let request = serde_json::json!({
+            "type": "settings_set",
+            "settings": {
+                "real_time_protection": settings.real_time_protection,
+                "scan_on_access": settings.scan_on_access,
+                "scan_archives": settings.scan_archives,
+                "auto_update": settings.auto_update,
+                "scan_schedule": settings.scan_schedule,
+                "scan_time": settings.scan_time,
+                "quarantine_path": settings.quarantine_path,
+                "exclusion_paths": settings.exclusion_paths
+            }
+        });
+
+        let response = self.send_request(MessageKind::Settings, request).await?;
+        
+        Ok(response.get("success").and_then(|v| v.as_bool()).unwrap_or(false))
+    }
+
+    async fn send_request(
+        &self,
+        kind: MessageKind,
+        request: serde_json::Value,
+    ) -> Result<serde_json::Value, ServiceClientError> {
+        let sealed_request = self.seal_message(kind, &request)?;
+        self.send_sealed(kind, sealed_request).await
+    }
+
+    /// Does the write-then-read exchange for an already-sealed frame. Split out of
+    /// `send_request` so callers with sensitive request bodies (e.g. `authenticate`) can seal
+    /// their own plaintext without ever handing it to `send_request` as a `serde_json::Value`.
+    async fn send_sealed(
+        &self,
+        kind: MessageKind,
+        sealed_request: Vec<u8>,
+    ) -> Result<serde_json::Value, ServiceClientError> {
+        // Hold the lock for the whole write-then-read exchange so a concurrent caller can't
+        // interleave its own frame on the same pipe handle in between.
+        let _request_guard = self.request_lock.lock().await;
+
+        // The actual I/O is synchronous; run it via `block_in_place` so it doesn't block
+        // whichever Tokio worker thread happens to be polling us for the round-trip.
+        tokio::task::block_in_place(|| {
+            // Convert HANDLE to std::fs::File for easier I/O
+            let mut file = unsafe {
+                std::fs::File::from_raw_handle(self.pipe_handle.0 as *mut std::ffi::c_void)
+            };
+
+            let io_result = (|| -> Result<serde_json::Value, ServiceClientError> {
+                write_frame(&mut file, &sealed_request)
+                    .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
+
+                let response_frame = read_frame(&mut file)
+                    .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
+
+                let (response_kind, body) = self.open_message(&response_frame)?;
+                if response_kind != kind {
+                    return Err(ServiceClientError::CommunicationError(format!(
+                        "response kind {response_kind:?} did not match request kind {kind:?}"
+                    )));
+                }
+
+                let response_str = std::str::from_utf8(&body)
+                    .map_err(|e| ServiceClientError::CommunicationError(e.to_string()))?;
+
+                serde_json::from_str(response_str)
+                    .map_err(|e| ServiceClientError::SerializationError(e.to_string()))
+            })();
+
+            // Forget the file so we don't close the handle
+            std::mem::forget(file);
+
+            io_result
+        })
+    }
+
+    /// Tags `value` with `kind` and seals it for the wire: `[kind byte || JSON bytes]`
+    /// encrypted under this connection's session key. The kind travels inside the encrypted
+    /// payload, not the length prefix, so a passive observer of the pipe can't even learn
+    /// what category of request is in flight.
+    ///
+    /// `value` is a plain `serde_json::Value`, not a `Secret`, so this is only safe for
+    /// request bodies that don't carry secrets - anything sensitive (e.g. a password) must
+    /// never be round-tripped through a `Value`, since its backing `String` isn't zeroized on
+    /// drop. Callers with sensitive fields should build their own JSON string directly and seal
+    /// it with `seal_json`, which they can then zeroize themselves too.
+    fn seal_message(
+        &self,
+        kind: MessageKind,
+        value: &serde_json::Value,
+    ) -> Result<Vec<u8>, ServiceClientError> {
+        let json = serde_json::to_string(value)
+            .map_err(|e| ServiceClientError::SerializationError(e.to_string()))?;
+        self.seal_json(kind, json)
+    }
+
+    /// Tags an already-serialized JSON request body with `kind` and seals it for the wire, as
+    /// `seal_message` does. Takes ownership of `json` so it can be zeroized once it has been
+    /// copied into the encrypted frame, rather than left to linger un-zeroized in whatever
+    /// container the caller originally serialized it into.
+    fn seal_json(&self, kind: MessageKind, mut json: String) -> Result<Vec<u8>, ServiceClientError> {
+        let mut plaintext = Vec::with_capacity(1 + json.len());
+        plaintext.push(u8::from(kind));
+        plaintext.extend_from_slice(json.as_bytes());
+
+        let sealed = self.channel.encrypt(&plaintext).map_err(|e| {
+            ServiceClientError::CommunicationError(format!("failed to encrypt message: {e}"))
+        });
+        // The plaintext (which may carry a password) has now been copied into the encrypted
+        // frame; don't leave it sitting around in these buffers any longer than necessary.
+        json.zeroize();
+        plaintext.zeroize();
+
+        sealed
+    }
+
+    /// Inverse of `seal_message`: decrypts a frame and splits off its kind byte.
+    fn open_message(&self, frame: &[u8]) -> Result<(MessageKind, Vec<u8>), ServiceClientError> {
+        let mut plaintext = self.channel.decrypt(frame).map_err(|e| {
+            ServiceClientError::CommunicationError(format!("failed to decrypt message: {e}"))
+        })?;
+
+        if plaintext.is_empty() {
+            return Err(ServiceClientError::CommunicationError(
+                "empty message frame".to_string(),
+            ));
+        }
+        let kind_byte = plaintext.remove(0);
+        let kind = MessageKind::try_from(kind_byte).map_err(|_| {
+            ServiceClientError::CommunicationError(format!("unknown message kind {kind_byte}"))
+        })?;
+
+        Ok((kind, plaintext))
+    }
+}
+
+impl Drop for ServiceClient {
+    fn drop(&mut self) {
+        if self.pipe_handle != INVALID_HANDLE_VALUE {
+            unsafe {
+                CloseHandle(self.pipe_handle);
+            }
+        }
+    }
+}
+
+/// Windows paths are case-insensitive; compare them that way instead of failing a
+/// legitimate peer over a casing difference in the install path.
+fn paths_equal(a: &Path, b: &Path) -> bool {
+    a.to_string_lossy().eq_ignore_ascii_case(&b.to_string_lossy())
+}